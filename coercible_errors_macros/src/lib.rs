@@ -0,0 +1,364 @@
+//! Procedural macro companion to `coercible_errors`.
+//!
+//! This crate provides the [`coerced`] attribute,
+//! which generates the boilerplate that the crate notes in `src/main.rs`
+//! describe as "beyond my macro skills":
+//! turning a function written against the error-carrying generic
+//! parameters of a "smart function" into the fully coerced signature.
+//!
+//! It also provides [`coercible_lattice!`], which generalizes the binary
+//! `Never`/`$error` relation generated by `coercible_errors!` into a
+//! user-declared join-semilattice of any number of error types.
+
+extern crate proc_macro;
+
+use std::collections::{HashMap, HashSet};
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_macro_input, parse_quote, GenericParam, Generics, Ident, ImplItem, Item, PathArguments,
+    ReturnType, Signature, Token, Type,
+};
+
+/// Finds the bounded type parameters of `generics`
+/// (per the `Producer`/`Consumer` convention,
+/// each such parameter exposes an associated `Error` type).
+fn bounded_type_params(generics: &Generics) -> Vec<Ident> {
+    generics
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            GenericParam::Type(tp) if !tp.bounds.is_empty() => Some(tp.ident.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Rewrites `sig`'s return type and where-clause in place,
+/// looking for its two error-carrying parameters among `sig`'s own
+/// generics, falling back to `outer_generics` (the enclosing `impl`
+/// block's generics) when `sig` itself introduces none of its own --
+/// which is the case for an associated function whose bounds are
+/// declared on the `impl` block rather than on the method itself.
+fn coerce_signature(sig: &mut Signature, outer_generics: Option<&Generics>) -> syn::Result<()> {
+    let mut error_params = bounded_type_params(&sig.generics);
+    if error_params.is_empty() {
+        if let Some(outer_generics) = outer_generics {
+            error_params = bounded_type_params(outer_generics);
+        }
+    }
+
+    let (p1, p2) = match &error_params[..] {
+        [p1, p2] => (p1, p2),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig.generics,
+                "#[coerced] expects exactly two bounded generic type parameters, \
+                 each exposing an associated `Error` type, on the function \
+                 itself or on its enclosing `impl` block",
+            ));
+        }
+    };
+
+    let value_ty = match &sig.output {
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Path(type_path) => type_path
+                .path
+                .segments
+                .last()
+                .and_then(|segment| match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => args.args.first().cloned(),
+                    _ => None,
+                }),
+            _ => None,
+        },
+        ReturnType::Default => None,
+    };
+    let value_ty = value_ty.unwrap_or_else(|| parse_quote!(()));
+
+    sig.output = parse_quote!(-> CoercedResult<#value_ty, #p1::Error, #p2::Error>);
+    sig.generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(#p1::Error: CoercibleWith<#p2::Error>));
+
+    Ok(())
+}
+
+/// Expands a function written against its generic parameters' `Error`
+/// associated types into the hand-written `CoercedResult` form.
+///
+/// The user writes:
+///
+/// ```ignore
+/// #[coerced]
+/// fn pipe<P: Producer, C: Consumer>(p: &P, c: &mut C) -> Result<()> {
+///     Ok(c.consume(p.produce()?)?)
+/// }
+/// ```
+///
+/// and this expands to:
+///
+/// ```ignore
+/// fn pipe<P: Producer, C: Consumer>(p: &P, c: &mut C) -> CoercedResult<(), P::Error, C::Error>
+/// where
+///     P::Error: CoercibleWith<C::Error>,
+/// {
+///     Ok(c.consume(p.produce()?)?)
+/// }
+/// ```
+///
+/// The error-carrying parameters are found by scanning the function's
+/// generics for bounded type parameters (per the `Producer`/`Consumer`
+/// convention, each such parameter exposes an associated `Error` type).
+/// Exactly two are expected, matching the binary `CoercibleWith` relation
+/// generated by `coercible_errors!`.
+///
+/// `#[coerced]` can also be applied to an `impl` block containing exactly
+/// one method, for the common case where the bounded type parameters
+/// (e.g. the `P1`, `P2` of `impl<P1: Producer, P2: Producer> Producer for
+/// PMax<P1, P2>`) live on the `impl` itself rather than on the method --
+/// an attribute placed directly on such a method has no way to see them,
+/// since a `#[proc_macro_attribute]` only ever receives the item it is
+/// attached to. The `CoercibleWith` bound is then added to the method's
+/// where-clause only; the `impl` block's own where-clause is left as
+/// written, so it must declare that bound itself (its `type Error` needs it):
+///
+/// ```ignore
+/// #[coerced]
+/// impl<P1: Producer, P2: Producer> Producer for PMax<P1, P2>
+/// where
+///     P1::Error: CoercibleWith<P2::Error>,
+/// {
+///     type Error = CoercedError<P1::Error, P2::Error>;
+///     fn produce(&self) -> Result<u16> {
+///         Ok(self.0.produce()?.max(self.1.produce()?))
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn coerced(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as Item);
+
+    match item {
+        Item::Fn(mut func) => match coerce_signature(&mut func.sig, None) {
+            Ok(()) => TokenStream::from(quote!(#func)),
+            Err(e) => e.to_compile_error().into(),
+        },
+        Item::Impl(mut imp) => {
+            let outer_generics = imp.generics.clone();
+            let mut methods: Vec<_> = imp
+                .items
+                .iter_mut()
+                .filter_map(|item| match item {
+                    ImplItem::Method(f) => Some(f),
+                    _ => None,
+                })
+                .collect();
+            let [method] = &mut methods[..] else {
+                return syn::Error::new_spanned(
+                    &imp.self_ty,
+                    "#[coerced] on an impl block expects exactly one method to rewrite",
+                )
+                .to_compile_error()
+                .into();
+            };
+            match coerce_signature(&mut method.sig, Some(&outer_generics)) {
+                Ok(()) => TokenStream::from(quote!(#imp)),
+                Err(e) => e.to_compile_error().into(),
+            }
+        }
+        other => syn::Error::new_spanned(
+            &other,
+            "#[coerced] can only be applied to a function or to an impl block",
+        )
+        .to_compile_error()
+        .into(),
+    }
+}
+
+/// A chain `A < B < C` in a [`coercible_lattice!`] declaration.
+struct Chain(Vec<Ident>);
+
+impl Parse for Chain {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let idents = Punctuated::<Ident, Token![<]>::parse_separated_nonempty(input)?;
+        Ok(Chain(idents.into_iter().collect()))
+    }
+}
+
+/// A full `coercible_lattice! { ... }` declaration: the name of the
+/// `$error` type already set up by `coercible_errors!`, followed by a
+/// list of chains describing edges of the partial order.
+struct Lattice {
+    error: Ident,
+    chains: Vec<Chain>,
+}
+
+impl Parse for Lattice {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let error = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let chains = Punctuated::<Chain, Token![;]>::parse_terminated(input)?;
+        Ok(Lattice {
+            error,
+            chains: chains.into_iter().collect(),
+        })
+    }
+}
+
+/// Declares a join-semilattice of error types and generates all pairwise
+/// `CoercibleWith` impls between them.
+///
+/// ```ignore
+/// coercible_lattice! {
+///     Error;
+///     Never < IoError < Error;
+///     ParseError < Error;
+/// }
+/// ```
+///
+/// The leading `Error;` names the `$error` type already set up by a prior
+/// `coercible_errors!` invocation in scope. Each chain `A < B < C` then
+/// declares that `A` coerces into `B`, which coerces into `C`, and so on.
+/// For every (possibly equal) pair of declared types `(A, B)`, this macro
+/// computes their least upper bound (LUB) by a transitive-closure walk
+/// over the declared edges — the ancestors of `A` and of `B` are
+/// collected, intersected, and the unique minimal element of that
+/// intersection is picked — then emits `impl CoercibleWith<B> for A {
+/// type Into = <the LUB>; }`. The resulting relation is commutative
+/// (`A`-with-`B` and `B`-with-`A` yield the same `Into`) and idempotent
+/// (`A`-with-`A` is `A`). If two declared types share no common upper
+/// bound (or more than one incomparable minimal one), this is reported as
+/// a compile error pointing at the offending pair.
+///
+/// The four pairs among `{Never, Error}` are skipped, since
+/// `coercible_errors!` already generates those `CoercibleWith` impls;
+/// declaring a lattice alongside it does not conflict with (or
+/// duplicate) them. As with `coercible_errors!`, callers are still
+/// responsible for providing the `From` conversions into each LUB.
+/// Whenever an impl actually lifts `A` into a strictly greater type (i.e.
+/// the LUB isn't `A` itself), the generated `coerce` method additionally
+/// requires the LUB to implement `coercible_errors::WithCause<A>`, so
+/// `A` is preserved as the LUB's `source()` rather than discarded --
+/// except when `A` is `Never`, in which case (just like the
+/// `Never`-to-`$error` impls generated by `coercible_errors!`) there is no
+/// `self` to ever preserve, so `coerce` is dead code (`match self {}`) and
+/// no `WithCause` bound is required.
+#[proc_macro]
+pub fn coercible_lattice(input: TokenStream) -> TokenStream {
+    let lattice = parse_macro_input!(input as Lattice);
+    let error_name = lattice.error.to_string();
+
+    let mut idents: HashMap<String, Ident> = HashMap::new();
+    let mut edges: HashMap<String, Vec<String>> = HashMap::new();
+    for chain in &lattice.chains {
+        for ident in &chain.0 {
+            idents
+                .entry(ident.to_string())
+                .or_insert_with(|| ident.clone());
+            edges.entry(ident.to_string()).or_default();
+        }
+        for pair in chain.0.windows(2) {
+            edges
+                .get_mut(&pair[0].to_string())
+                .unwrap()
+                .push(pair[1].to_string());
+        }
+    }
+
+    let names: Vec<String> = idents.keys().cloned().collect();
+    let already_covered = |name: &str| name == "Never" || name == error_name;
+
+    // Transitive closure: ancestors(X) is the set of types reachable from
+    // X by following declared edges, including X itself.
+    let mut ancestors: HashMap<String, HashSet<String>> = HashMap::new();
+    for name in &names {
+        let mut seen = HashSet::new();
+        let mut stack = vec![name.clone()];
+        seen.insert(name.clone());
+        while let Some(cur) = stack.pop() {
+            for next in &edges[&cur] {
+                if seen.insert(next.clone()) {
+                    stack.push(next.clone());
+                }
+            }
+        }
+        ancestors.insert(name.clone(), seen);
+    }
+
+    let mut impls = Vec::new();
+    for a in &names {
+        for b in &names {
+            if already_covered(a) && already_covered(b) {
+                // `coercible_errors!` already generates this exact impl
+                // for the `Never`/`$error` pair; skip it here to avoid
+                // a conflicting-implementation error.
+                continue;
+            }
+
+            let common: Vec<String> = ancestors[a]
+                .intersection(&ancestors[b])
+                .cloned()
+                .collect();
+
+            let minimal: Vec<&String> = common
+                .iter()
+                .filter(|&c| common.iter().all(|d| d == c || ancestors[c].contains(d)))
+                .collect();
+
+            let lub = match minimal.as_slice() {
+                [lub] => *lub,
+                _ => {
+                    let message = format!(
+                        "`{}` and `{}` have no unique common supertype in this lattice",
+                        a, b
+                    );
+                    return syn::Error::new(idents[a].span(), message)
+                        .to_compile_error()
+                        .into();
+                }
+            };
+
+            let ident_a = &idents[a];
+            let ident_b = &idents[b];
+            let ident_lub = &idents[lub];
+
+            // `Never` is uninhabited, so there is never a `self` to
+            // preserve: like the `Never -> $error` impls generated by
+            // `coercible_errors!` itself, this is dead code regardless of
+            // the join, and gets no `WithCause` bound (the join still needs
+            // `From<Never>`, as the trait's `Into` bounds require).
+            // Otherwise, when `A` already *is* the join, coercing it is the
+            // identity: there is no other value to preserve as a
+            // `source()`. When `A` is lifted into a strictly greater type,
+            // thread it through as the cause instead of discarding it.
+            let coerce_body = if a == "Never" {
+                quote! { match self {} }
+            } else if lub == a {
+                quote! { self }
+            } else {
+                quote! { <#ident_lub as WithCause<#ident_a>>::from_cause(self) }
+            };
+            let where_clause = if a == "Never" || lub == a {
+                quote! {}
+            } else {
+                quote! { where #ident_lub: WithCause<#ident_a> }
+            };
+
+            impls.push(quote! {
+                impl CoercibleWith<#ident_b> for #ident_a #where_clause {
+                    type Into = #ident_lub;
+                    fn coerce(self) -> #ident_lub {
+                        #coerce_body
+                    }
+                }
+            });
+        }
+    }
+
+    TokenStream::from(quote! { #(#impls)* })
+}
@@ -1,3 +1,5 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 //! Zero-cost error handling for generic traits.
 //!
 //! # Rationale
@@ -19,12 +21,24 @@
 //! See `README.md` for a more detailed explaination.
 //!
 //! [`coercible_errors!`]: macro.coercible_errors.html
+//!
+//! # `no_std` support
+//!
+//! `no_std` support is available by disabling the default `std` feature
+//! (declared as `default = ["std"]` under `[features]` in `Cargo.toml`);
+//! either way, `coercible_errors!` generates bounds against
+//! [`core::error::Error`](https://doc.rust-lang.org/core/error/trait.Error.html),
+//! so the `CoercibleWith` join logic is identical in both configurations.
+
+#[cfg(not(feature = "std"))]
+extern crate core as std;
 
 /// Sets up coercible_errors for a previously defined error type.
 ///
 /// It re-exports the types [`Never`] and [`OkResult`],
-/// and defines three new traits and types `CoercibleWith`,
-/// `CoercedError` and `CoercedResult`.
+/// and defines the traits and types `CoercibleWith`, `CoercedError` and
+/// `CoercedResult` (for joining two error types), as well as `CoerceAll`
+/// and `CoercedResultN` (for joining a tuple of up to 16 error types).
 ///
 /// [`Never`]: enum.Never.html
 /// [`OkResult`]: type.OkResult.html
@@ -37,7 +51,31 @@ macro_rules! coercible_errors {
         coercible_errors!($error, CoercibleWith, CoercedError, CoercedResult);
     };
     ($error: ty, $coercible_with: ident, $coerced_error: ident, $coerced_result: ident) => {
+        coercible_errors!(
+            $error,
+            $coercible_with,
+            $coerced_error,
+            $coerced_result,
+            CoerceAll,
+            CoercedResultN
+        );
+    };
+    (
+        $error: ty,
+        $coercible_with: ident,
+        $coerced_error: ident,
+        $coerced_result: ident,
+        $coerce_all: ident,
+        $coerced_result_n: ident
+    ) => {
         pub use $crate::{Never, OkResult};
+        // `WithCause` is only needed by callers that also use
+        // `coercible_lattice!`; re-exported unconditionally (rather than
+        // behind the `macros` feature) since a `cfg` written inside this
+        // exported macro would be evaluated against the *expanding*
+        // crate's features, not this crate's own.
+        #[allow(unused_imports)]
+        pub use $crate::WithCause;
 
         // This conversion can never happen (since Never can have no value),
         // but it is required for allowing $error and Never to coerce with each other.
@@ -59,18 +97,45 @@ macro_rules! coercible_errors {
                 + From<Self>
                 + From<E>
                 + $coercible_with<$error>;
+
+            /// Coerces `self` into `Self::Into`.
+            ///
+            /// This is an opt-in alternative to relying on `?`/`From` alone:
+            /// unlike the `From` conversion (which is free to rebuild the
+            /// target value from scratch), `coerce` is the method that a
+            /// multi-type lattice (see [`$crate::coercible_lattice!`])
+            /// threads through [`WithCause`](crate::WithCause) to preserve
+            /// `self` as the [`source()`](std::error::Error::source) of the
+            /// result instead of discarding it. Here, where the only two
+            /// error types involved are `$error` and `Never`, there is
+            /// nothing to preserve: the `$error`-to-`$error` path is the
+            /// identity, and the `Never`-to-`$error` path is statically
+            /// dead code.
+            fn coerce(self) -> Self::Into;
         }
         impl $coercible_with<$error> for $error {
             type Into = $error;
+            fn coerce(self) -> $error {
+                self
+            }
         }
         impl $coercible_with<Never> for $error {
             type Into = $error;
+            fn coerce(self) -> $error {
+                self
+            }
         }
         impl $coercible_with<$error> for Never {
             type Into = $error;
+            fn coerce(self) -> $error {
+                match self {}
+            }
         }
         impl $coercible_with<Never> for Never {
             type Into = Never;
+            fn coerce(self) -> Never {
+                self
+            }
         }
 
         /// A shortcut for building the coerced error type,
@@ -82,31 +147,111 @@ macro_rules! coercible_errors {
         /// given one value type and two error types,
         /// which must both be either `$error` or `Never`.
         pub type $coerced_result<T, E1, E2> = std::result::Result<T, $coerced_error<E1, E2>>;
+
+        /// A trait for coercing a tuple of up to 16 error types into their join,
+        /// computed by right-folding `$coercible_with` over the tuple:
+        /// a single-element tuple `(E1,)` joins to `E1` itself,
+        /// and `(E1, Rest...)` joins to `$coerced_error<E1, <Rest as $coerce_all>::Into>`.
+        ///
+        /// As a result, the join is `Never` only when *every* member of the tuple
+        /// is `Never`, and `$error` otherwise, so an all-infallible combination of
+        /// producers or consumers remains zero-cost.
+        pub trait $coerce_all {
+            /// The first error type of the tuple, i.e. what a generic caller's
+            /// `?` operator actually needs to convert from.
+            type Head;
+
+            /// Like `$coercible_with`'s own `Into`, this carries the
+            /// `From<Self::Head>` guarantee, so that `?` type-checks even
+            /// when the tuple is only known through this trait generically.
+            type Into: std::marker::Send + std::error::Error + 'static + From<Self::Head>;
+        }
+
+        /// A shortcut for building the coerced result type of a tuple of
+        /// (up to 16) error types, given one value type.
+        pub type $coerced_result_n<T, Es> = std::result::Result<T, <Es as $coerce_all>::Into>;
+
+        $crate::__coercible_errors_coerce_all! {
+            $coercible_with, $coerced_error, $coerce_all, $error;
+            E1 E2 E3 E4 E5 E6 E7 E8 E9 E10 E11 E12 E13 E14 E15 E16
+        }
     };
 }
 
-/// An "error" type that can never happen.
+/// Recursively generates [`CoerceAll`]-like impls for tuples of decreasing arity,
+/// from the given list of type-parameter names down to a single one.
 ///
-/// NB: once the [`never`] types reaches *stable*,
-/// this type will be an alias for the standard type.
+/// Not part of the public API; used internally by [`coercible_errors!`].
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __coercible_errors_coerce_all {
+    ($coercible_with: ident, $coerced_error: ident, $coerce_all: ident, $error: ty; $head: ident) => {
+        impl<$head> $coerce_all for ($head,)
+        where
+            $head: $coercible_with<$error>,
+        {
+            type Head = $head;
+            type Into = $head;
+        }
+    };
+    ($coercible_with: ident, $coerced_error: ident, $coerce_all: ident, $error: ty; $head: ident $($tail: ident)+) => {
+        impl<$head, $($tail),+> $coerce_all for ($head, $($tail),+)
+        where
+            $head: $coercible_with<$error>,
+            ($($tail,)+): $coerce_all,
+            $head: $coercible_with<<($($tail,)+) as $coerce_all>::Into>,
+        {
+            type Head = $head;
+            type Into = $coerced_error<$head, <($($tail,)+) as $coerce_all>::Into>;
+        }
+
+        $crate::__coercible_errors_coerce_all! {
+            $coercible_with, $coerced_error, $coerce_all, $error;
+            $($tail)+
+        }
+    };
+}
+
+/// An "error" type that can never happen.
 ///
-/// [`never`]: https://doc.rust-lang.org/std/primitive.never.html
+/// This is an alias for [`core::convert::Infallible`],
+/// so that `OkResult<T>` interoperates with the wider ecosystem's
+/// `Result<T, Infallible>`: the `?` operator composes cleanly even
+/// when one branch of a "smart function" is infallible and another
+/// yields `$error`, with no manual `.map_err` required.
 ///
-#[derive(Clone, Debug)]
-pub enum Never {}
-impl ::std::fmt::Display for Never {
-    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        write!(f, "Never")
-    }
-}
-impl std::error::Error for Never {}
+/// [`core::convert::Infallible`]: https://doc.rust-lang.org/core/convert/enum.Infallible.html
+pub type Never = std::convert::Infallible;
 
 /// Type alias for a result that will Never fail.
 pub type OkResult<T> = std::result::Result<T, Never>;
 
+/// Implemented by an error type that can be built from another error type
+/// while recording it so it is later returned by
+/// [`source()`](std::error::Error::source), instead of discarding it.
+///
+/// `coercible_lattice!` requires this bound, on the joined type, whenever
+/// it generates a `CoercibleWith::coerce` body that actually lifts an error
+/// into a strictly greater one in the declared lattice, so that crossing
+/// that abstraction boundary preserves the causal chain.
+pub trait WithCause<E>: From<E> {
+    /// Builds `Self` from `cause`, recording it as the error's `source()`.
+    fn from_cause(cause: E) -> Self;
+}
+
 #[cfg(feature = "example_generated")]
 pub mod example_generated;
 
+/// Generates the boilerplate of a "smart function" from its error-carrying
+/// generic parameters. See `coercible_errors_macros::coerced` for details.
+#[cfg(feature = "macros")]
+pub use coercible_errors_macros::coerced;
+
+/// Declares a join-semilattice of error types and their `CoercibleWith`
+/// relations. See `coercible_errors_macros::coercible_lattice` for details.
+#[cfg(feature = "macros")]
+pub use coercible_errors_macros::coercible_lattice;
+
 #[cfg(test)]
 #[macro_use]
 extern crate error_chain;
@@ -1,4 +1,8 @@
 mod error {
+    // error_chain's own macro expansion checks a cfg that isn't declared
+    // anywhere in this crate; harmless, but trips `unexpected_cfgs`.
+    #![allow(unexpected_cfgs)]
+
     error_chain! {
         errors {
             Producer {
@@ -7,10 +11,64 @@ mod error {
             Consumer {
                 description("error occurent in consumer"),
             }
+            Io {
+                description("I/O error"),
+            }
         }
     }
     coercible_errors! {}
 
+    /// A foreign error type, joined into `Error` via [`coercible_lattice!`]
+    /// below rather than via the `Never`/`Error` relation that
+    /// `coercible_errors!` already sets up.
+    #[cfg(feature = "macros")]
+    #[derive(Debug)]
+    pub struct IoError(pub String);
+
+    #[cfg(feature = "macros")]
+    impl std::fmt::Display for IoError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "io error: {}", self.0)
+        }
+    }
+
+    #[cfg(feature = "macros")]
+    impl std::error::Error for IoError {}
+
+    #[cfg(feature = "macros")]
+    impl From<IoError> for Error {
+        fn from(e: IoError) -> Error {
+            Error::with_chain(e, ErrorKind::Io)
+        }
+    }
+
+    #[cfg(feature = "macros")]
+    impl WithCause<IoError> for Error {
+        fn from_cause(cause: IoError) -> Error {
+            cause.into()
+        }
+    }
+
+    // Required by `coercible_lattice!` for the `Never < IoError` edge
+    // below: `Never` being uninhabited, this is dead code, but the
+    // `CoercibleWith<IoError>::Into` bound still requires `IoError` to
+    // implement `From<Never>` (no `WithCause` bound is required, unlike
+    // for the `IoError < Error` edge above).
+    #[cfg(feature = "macros")]
+    impl From<Never> for IoError {
+        fn from(never: Never) -> IoError {
+            match never {}
+        }
+    }
+
+    #[cfg(feature = "macros")]
+    use crate::coercible_lattice;
+
+    #[cfg(feature = "macros")]
+    coercible_lattice! {
+        Error;
+        Never < IoError < Error;
+    }
 }
 
 use self::error::*;
@@ -90,6 +148,52 @@ where
     }
 }
 
+#[cfg(feature = "macros")]
+pub struct PMax2<P1, P2>(P1, P2);
+
+/// Same as `PMax`, but with its "smart" signature generated by
+/// `#[coerced]` applied to the whole `impl` block, since here the bounded
+/// generics (`P1`, `P2`) live on the `impl`, not on `produce` itself.
+#[cfg(feature = "macros")]
+#[coercible_errors_macros::coerced]
+impl<P1: Producer, P2: Producer> Producer for PMax2<P1, P2>
+where
+    P1::Error: CoercibleWith<P2::Error>,
+{
+    type Error = CoercedError<P1::Error, P2::Error>;
+    fn produce(&self) -> Result<u16> {
+        Ok(self.0.produce()?.max(self.1.produce()?))
+    }
+}
+
+/// Like `PMax`, but over a slice of any number of producers of one type `P`;
+/// its error type is the `CoerceAll` join of the one-element tuple `(P::Error,)`.
+fn produce_max<P: Producer>(producers: &[P]) -> CoercedResultN<u16, (P::Error,)>
+where
+    (P::Error,): CoerceAll<Head = P::Error>,
+{
+    producers
+        .iter()
+        .map(Producer::produce)
+        .try_fold(0, |max, v| Ok(max.max(v?)))
+}
+
+/// Same idea as `produce_max`, but joins *two distinct* producers' error
+/// types in a single tuple, exercising `CoerceAll`'s actual recursive case
+/// (`$head: CoercibleWith<<Rest as CoerceAll>::Into>`) rather than its
+/// one-element base case.
+fn produce_max2<P1: Producer, P2: Producer>(p1: &P1, p2: &P2) -> CoercedResultN<u16, (P1::Error, P2::Error)>
+where
+    (P1::Error, P2::Error): CoerceAll<Head = P1::Error>,
+    <(P1::Error, P2::Error) as CoerceAll>::Into: From<P2::Error>,
+{
+    let v1 = p1.produce()?;
+    let v2 = p2
+        .produce()
+        .map_err(<<(P1::Error, P2::Error) as CoerceAll>::Into>::from)?;
+    Ok(v1.max(v2))
+}
+
 /// This is the naive version of pipe;
 /// it always returns a `Result`,
 /// even if both the producer and the consumer return `OkResult`s.
@@ -108,6 +212,14 @@ where
     Ok(c.consume(p.produce()?)?)
 }
 
+/// Same as `pipe2`, but with its "smart" signature generated by
+/// `#[coerced]` instead of being written out by hand.
+#[cfg(feature = "macros")]
+#[coercible_errors_macros::coerced]
+fn pipe3<P: Producer, C: Consumer>(p: &P, c: &mut C) -> Result<()> {
+    Ok(c.consume(p.produce()?)?)
+}
+
 #[test]
 fn test() -> Result<()> {
     // NB: most of this test is actually performed at compile time:
@@ -146,12 +258,53 @@ fn test() -> Result<()> {
     let _r: Result<()>   = pipe2(&0x20000_u32, &mut cons8);
     let _r: Result<()>   = pipe2(&0x200_u16, &mut cons8);
 
+    // ########## pipe3 ##########
+    // pipe3 is pipe2, generated by #[coerced]
+    #[cfg(feature = "macros")]
+    {
+        let _r: OkResult<()> = pipe3(&42_u16, &mut cons16);
+        let _r: Result<()>   = pipe3(&42_u16, &mut cons8);
+        let _r: Result<()>   = pipe3(&42_u32, &mut cons16);
+        let _r: Result<()>   = pipe3(&42_u32, &mut cons8);
+
+        let _r: Result<()>   = pipe3(&0x20000_u32, &mut cons8);
+        let _r: Result<()>   = pipe3(&0x200_u16, &mut cons8);
+    }
+
     // ######## PMax ########
     let _r: OkResult<u16> = PMax(42_u16, 1_u16).produce();
     let _r: Result<u16>   = PMax(42_u32, 1_u16).produce();
     let _r: Result<u16>   = PMax(42_u16, 1_u32).produce();
     let _r: Result<u16>   = PMax(42_u32, 1_u32).produce();
 
+    // ######## PMax2 ########
+    // PMax2 is PMax, with its `produce` signature generated by #[coerced]
+    // applied to the `impl` block
+    #[cfg(feature = "macros")]
+    {
+        let _r: OkResult<u16> = PMax2(42_u16, 1_u16).produce();
+        let _r: Result<u16>   = PMax2(42_u32, 1_u16).produce();
+        let _r: Result<u16>   = PMax2(42_u16, 1_u32).produce();
+        let _r: Result<u16>   = PMax2(42_u32, 1_u32).produce();
+    }
+
+    // ######## produce_max (CoerceAll over a tuple) ########
+    let _r: OkResult<u16> = produce_max(&[1_u16, 42_u16]);
+    let _r: Result<u16>   = produce_max(&[1_u32, 42_u32]);
+    assert_eq!(produce_max(&[1_u16, 42_u16]).unwrap(), 42);
+    assert!(produce_max(&[1_u32, 0x20000_u32]).is_err());
+
+    // ######## produce_max2 (CoerceAll over a mixed 2-tuple) ########
+    let _r: OkResult<u16> = produce_max2(&1_u16, &42_u16);
+    let _r: Result<u16>   = produce_max2(&1_u32, &42_u16);
+    let _r: Result<u16>   = produce_max2(&1_u16, &42_u32);
+    let _r: Result<u16>   = produce_max2(&1_u32, &42_u32);
+    assert_eq!(produce_max2(&1_u16, &42_u16).unwrap(), 42);
+    assert_eq!(produce_max2(&1_u32, &42_u16).unwrap(), 42);
+    assert_eq!(produce_max2(&1_u16, &42_u32).unwrap(), 42);
+    assert!(produce_max2(&0x20000_u32, &1_u16).is_err());
+    assert!(produce_max2(&1_u16, &0x20000_u32).is_err());
+
     // ######## testing the returned values ########
     // (having the correct type is not enough...)
 
@@ -197,7 +350,62 @@ fn test() -> Result<()> {
     assert!(r3.unwrap() == 42);
     assert!(r4.unwrap() == 42);
 
+    #[cfg(feature = "macros")]
+    {
+        let r1: OkResult<u16> = PMax2(42_u16, 1_u16).produce();
+        let r2: Result<u16>   = PMax2(42_u32, 1_u16).produce();
+        let r3: Result<u16>   = PMax2(42_u16, 1_u32).produce();
+        let r4: Result<u16>   = PMax2(42_u32, 1_u32).produce();
+        assert!(r1.unwrap() == 42);
+        assert!(r2.unwrap() == 42);
+        assert!(r3.unwrap() == 42);
+        assert!(r4.unwrap() == 42);
+    }
+
     println!("All tests passed",);
 
     Ok(())
 }
+
+/// For the base `$error`/`Never` relation (no `coercible_lattice!` involved),
+/// `coerce()` has nothing to preserve: the `Error`-to-`Error` path is the
+/// identity, just like plain `?` would give us.
+#[test]
+fn test_coerce_is_identity_for_the_binary_relation() {
+    let e: Error = "boom".into();
+    let coerced: Error = CoercibleWith::<Error>::coerce(e);
+    assert_eq!(coerced.to_string(), "boom");
+}
+
+/// Coercing across a `coercible_lattice!`-declared join (rather than the
+/// binary `$error`/`Never` relation) should preserve the original error
+/// as the joined error's `source()`, instead of discarding it.
+#[cfg(feature = "macros")]
+#[test]
+fn test_lattice_coerce_preserves_source() {
+    use self::error::IoError;
+    use std::error::Error as _;
+
+    let io_err = IoError("disk full".to_string());
+    let coerced: Error = CoercibleWith::<Error>::coerce(io_err);
+
+    let source = coerced.source().expect("coerce() should preserve the cause");
+    assert_eq!(source.to_string(), "io error: disk full");
+}
+
+/// The `Never < IoError` edge declared above joins `Never` with a type
+/// other than `$error`. Since `Never` is uninhabited, `coerce` for this
+/// pair is dead code and requires no `WithCause` bound -- only the
+/// `From<Never>` impl provided above. This is a compile-time check: there
+/// is no `Never` value to actually call `coerce` with.
+#[cfg(feature = "macros")]
+#[test]
+fn test_lattice_coerce_from_never_needs_no_with_cause() {
+    use self::error::IoError;
+
+    fn _assert_never_coerces_into_io_error()
+    where
+        Never: CoercibleWith<IoError, Into = IoError>,
+    {
+    }
+}